@@ -2,10 +2,12 @@
 //!
 //! Use by passing `hyper::server::MakeService` instances to a `CompositeMakeService`
 //! together with the base path for requests that should be handled by that service.
+use futures::{future, Future};
+use hyper::header::ALLOW;
 use hyper::service::Service;
-use hyper::{Request, Response, StatusCode};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use std::fmt;
 use std::ops::{Deref, DerefMut};
-use std::{fmt, io};
 
 /// Trait for generating a default "not found" response. Must be implemented on
 /// the `Response` associated type for `MakeService`s being combined in a
@@ -15,7 +17,7 @@ pub trait NotFound<V> {
     fn not_found() -> hyper::Response<V>;
 }
 
-impl <B: Default> NotFound<Body> for Body {
+impl NotFound<Body> for Body {
     fn not_found() -> hyper::Response<Body> {
         Response::builder()
             .status(StatusCode::NOT_FOUND)
@@ -24,13 +26,98 @@ impl <B: Default> NotFound<Body> for Body {
     }
 }
 
-/// Wraps a vector of pairs, each consisting of a base path as a `&'static str`
-/// and a `MakeService` instance. Implements `Deref<Vec>` and `DerefMut<Vec>` so
-/// these can be manipulated using standard `Vec` methods.
+/// Trait for generating a "method not allowed" response, including an
+/// `Allow` header listing the methods a matched base path does accept. Must
+/// be implemented on the `Response` associated type for `MakeService`s being
+/// combined in a `CompositeMakeService`.
+pub trait MethodNotAllowed<V> {
+    /// Return a "method not allowed" response advertising `allowed_methods`.
+    fn method_not_allowed(allowed_methods: &[Method]) -> hyper::Response<V>;
+}
+
+impl MethodNotAllowed<Body> for Body {
+    fn method_not_allowed(allowed_methods: &[Method]) -> hyper::Response<Body> {
+        let allow = allowed_methods
+            .iter()
+            .map(Method::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(ALLOW, allow)
+            .body(Body::default())
+            .unwrap()
+    }
+}
+
+/// Trait implemented by request representations that `CompositeService` can
+/// route, following tower's move to a generic `Service<Request>` parameter
+/// rather than pinning the request type to `hyper::Request<ReqBody>`. This
+/// lets the same `CompositeService` accept `Request<Body>` directly, or a
+/// context-wrapped `(Request<Body>, Context)` as used elsewhere in
+/// swagger-rs, without needing a separate composite type for each. Routing
+/// only ever needs a borrow of the request (via `http_request`); only
+/// rewriting the URI when delegating to a nested service needs to consume
+/// and rebuild it, via `map_request`.
+pub trait HttpRequest {
+    /// The request body type.
+    type Body;
+
+    /// Borrow the underlying `hyper::Request`, used for routing and method
+    /// matching without taking ownership of the request.
+    fn http_request(&self) -> &Request<Self::Body>;
+
+    /// Rebuild this request around a transformed `hyper::Request`, e.g.
+    /// after stripping a matched base path from its URI.
+    fn map_request<F>(self, f: F) -> Self
+    where
+        F: FnOnce(Request<Self::Body>) -> Request<Self::Body>;
+}
+
+impl<Body> HttpRequest for Request<Body> {
+    type Body = Body;
+
+    fn http_request(&self) -> &Request<Body> {
+        self
+    }
+
+    fn map_request<F>(self, f: F) -> Self
+    where
+        F: FnOnce(Request<Body>) -> Request<Body>,
+    {
+        f(self)
+    }
+}
+
+impl<Body, Context> HttpRequest for (Request<Body>, Context) {
+    type Body = Body;
+
+    fn http_request(&self) -> &Request<Body> {
+        &self.0
+    }
+
+    fn map_request<F>(self, f: F) -> Self
+    where
+        F: FnOnce(Request<Body>) -> Request<Body>,
+    {
+        let (req, context) = self;
+        (f(req), context)
+    }
+}
+
+/// Wraps a vector of entries, each consisting of a base path as a `&'static
+/// str`, a `MakeService` instance, a [`MountKind`] and the HTTP methods
+/// accepted at that base path (empty meaning "accept all"). Implements
+/// `Deref<Vec>` and `DerefMut<Vec>` so entries can be inspected or removed
+/// using standard `Vec` methods; use `push`/`push_nested` to add entries.
 ///
 /// The `Service` returned by calling `make_service()` will pass an incoming
-/// request to the first `Service` in the list for which the associated
-/// base path is a prefix of the request path.
+/// request to the `Service` in the list whose base path is the longest
+/// segment-wise match for the request path, e.g. a base path of `/foo` will
+/// match `/foo` and `/foo/bar`, but not `/foobar`, and a base path of
+/// `/api/v2/foo` is preferred over `/api/v2` for a request to
+/// `/api/v2/foo/bar`. Base paths do not need to be pushed in any particular
+/// order for this to work.
 ///
 /// Example Usage
 /// =============
@@ -40,50 +127,205 @@ impl <B: Default> NotFound<Body> for Body {
 /// let my_make_service2 = MakeService2::new();
 ///
 /// let mut composite_make_service = CompositeMakeService::new();
-/// composite_make_service.push(("/base/path/1", my_make_service1));
-/// composite_make_service.push(("/base/path/2", my_make_service2));
+/// composite_make_service.push("/base/path/1", &[Method::GET], my_make_service1);
+/// composite_make_service.push_nested("/base/path/2", &[], my_make_service2);
 ///
 /// // use as you would any `MakeService` instance
 /// ```
-type CompositedService<ReqBody, ResBody, Error> = Box<dyn Service<Request<ReqBody>, Response=Response<ResBody>, Error=Error>>;
-type CompositeMakeSeviceVec<T, SE, ReqBody, ResBody, RE> = Vec<(&'static str, Box<dyn Service<T, Error=SE, Response=CompositedService<ReqBody, ResBody, RE>>>)>;
+type CompositedService<Req, ResBody, Error> = Box<dyn Service<Req, Response=Response<ResBody>, Error=Error>>;
+type CompositeMakeServiceVec<T, SE, Req, ResBody, RE> = Vec<(&'static str, Box<dyn Service<T, Error=SE, Response=CompositedService<Req, ResBody, RE>>>, MountKind, &'static [Method])>;
 
-#[derive(Default)]
-pub struct CompositeMakeService<Target, ServiceError, ReqBody, ResBody, ReqError>
+/// Controls how `CompositeService` rewrites a matched request's URI before
+/// handing it to the inner service.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MountKind {
+    /// Pass the request through with its URI untouched.
+    Full,
+    /// Strip the matched base path from the request URI before delegating,
+    /// mirroring how axum's `nest` exposes routes relative to their mount
+    /// point. The original path (and query) is stashed in a [`FullPath`]
+    /// request extension.
+    Nested,
+}
+
+/// Policy controlling how a composite's `poll_ready` aggregates readiness
+/// from its mounted services, selectable via
+/// `CompositeMakeService::with_readiness_policy`. Since routing only picks
+/// one service per request, honoring backpressure from every mounted
+/// service is not strictly required to make progress on a single request,
+/// but is needed for the composite to report accurate readiness to layers
+/// (like load-shedding or buffering) that depend on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadinessPolicy {
+    /// `poll_ready` reports ready only once every mounted service does.
+    ReadyAll,
+    /// `poll_ready` always reports ready immediately, without polling any
+    /// mounted service. The service chosen for a given request is then
+    /// called without its own `poll_ready` being checked first.
+    ReadyAny,
+}
+
+impl Default for ReadinessPolicy {
+    fn default() -> Self {
+        ReadinessPolicy::ReadyAny
+    }
+}
+
+/// The original request path and query, stashed in a request extension by
+/// `CompositeService` whenever it strips a matched base path from a request
+/// mounted with `push_nested`. Downstream handlers that need the full,
+/// un-stripped path can recover it with `req.extensions().get::<FullPath>()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FullPath(pub String);
+
+/// Middleware that can be applied uniformly to every service inside a
+/// `CompositeMakeService` via [`CompositeMakeService::layer`], analogous to
+/// `tower::Layer` and actix-web's `Transform`/`wrap`.
+pub trait Layer<Req, ResBody, Error> {
+    /// The wrapped service produced by applying this layer.
+    type Service: Service<Req, Response = Response<ResBody>, Error = Error> + 'static;
+
+    /// Wrap `inner` with this layer's middleware.
+    fn layer(&self, inner: CompositedService<Req, ResBody, Error>) -> Self::Service;
+}
+
+pub struct CompositeMakeService<Target, ServiceError, Req, ResBody, ReqError>
 {
-    inner: CompositeMakeServiceVec,
-    phantom: PhantomData<(ServiceError, ReqBody, ResBody, ReqError)>
+    inner: CompositeMakeServiceVec<Target, ServiceError, Req, ResBody, ReqError>,
+    policy: ReadinessPolicy,
 }
 
-impl<Target, ServiceError, ReqBody, ResBody, ReqError> CompositeMakeService<Target, ServiceError, ReqBody, ResBody, ReqError> {
-    /// create an empty `CompositeMakeService`
+impl<Target, ServiceError, Req, ResBody, ReqError> CompositeMakeService<Target, ServiceError, Req, ResBody, ReqError> {
+    /// create an empty `CompositeMakeService` whose services report ready
+    /// immediately without polling any mounted service (`ReadyAny`),
+    /// matching this constructor's prior behavior.
+    /// Use `with_readiness_policy` to require every mounted service to be
+    /// ready before the composite does (`ReadyAll`).
     pub fn new() -> Self {
+        Self::with_readiness_policy(ReadinessPolicy::ReadyAny)
+    }
+
+    /// create an empty `CompositeMakeService` using the given
+    /// [`ReadinessPolicy`] to aggregate readiness across mounted services
+    pub fn with_readiness_policy(policy: ReadinessPolicy) -> Self {
         CompositeMakeService {
-          inner: Vec::new()
+          inner: Vec::new(),
+          policy,
         }
     }
+
+    /// Mount `service` at `base_path`. Matching requests are routed through
+    /// to `service` with their URI untouched. `methods` lists the HTTP
+    /// methods `service` accepts at this base path; a request whose path
+    /// matches but whose method isn't in `methods` falls through to the
+    /// next-longest mount whose base path also matches (if any) before
+    /// giving up with a `405 Method Not Allowed`, so registering a narrower
+    /// mount (e.g. `/foo/bar` for `POST` only) alongside a broader one (e.g.
+    /// `/foo` for `GET`) doesn't 405 a `GET /foo/bar` that the broader mount
+    /// would have accepted. Pass an empty slice to accept every method (the
+    /// previous, 404-only behavior).
+    pub fn push<MS>(&mut self, base_path: &'static str, methods: &'static [Method], service: MS)
+    where
+        MS: Service<Target, Error = ServiceError, Response = CompositedService<Req, ResBody, ReqError>> + 'static,
+    {
+        self.inner.push((base_path, Box::new(service), MountKind::Full, methods));
+    }
+
+    /// Like `push`, but strips `base_path` from the request URI before
+    /// calling `service`, so the same generated API service can be mounted
+    /// under different prefixes without regenerating its routes. The
+    /// original request path is stashed in a `FullPath` request extension.
+    pub fn push_nested<MS>(&mut self, base_path: &'static str, methods: &'static [Method], service: MS)
+    where
+        MS: Service<Target, Error = ServiceError, Response = CompositedService<Req, ResBody, ReqError>> + 'static,
+    {
+        self.inner.push((base_path, Box::new(service), MountKind::Nested, methods));
+    }
+
+    /// Wrap every service produced by `make_service()` with `layer`. This
+    /// lets cross-cutting concerns (auth, request-id injection, logging,
+    /// timeout) be added once at the composite level rather than baked into
+    /// each generated service.
+    pub fn layer<L>(self, layer: L) -> LayeredCompositeMakeService<Target, ServiceError, Req, ResBody, ReqError, L>
+    where
+        L: Layer<Req, ResBody, ReqError>,
+    {
+        LayeredCompositeMakeService { inner: self, layer }
+    }
 }
 
-impl<Target, ServiceError, ReqBody, ResBody, ReqError> Service<Target> for CompositeMakeService<Target, ServiceError, ReqBody, ResBody, ReqError>
+impl<Target, ServiceError, Req, ResBody, ReqError> Service<Target> for CompositeMakeService<Target, ServiceError, Req, ResBody, ReqError>
+where
+    Target: Clone,
+    ResBody: NotFound<ResBody> + MethodNotAllowed<ResBody> + 'static,
 {
     type Error = ServiceError;
-    type Response = CompositeService<ReqBody, ResBody, ReqError>;
-    type Future = futures::future::FutureResult<Self::Service, io::Error>;
+    type Response = CompositeService<Req, ResBody, ReqError>;
+    type Future = Box<dyn Future<Item = Self::Response, Error = Self::Error>>;
+
+    fn poll_ready(&mut self) -> futures::Poll<(), Self::Error> {
+        // Producing a new `CompositeService` doesn't depend on the
+        // readiness of the services it will eventually wrap.
+        Ok(futures::Async::Ready(()))
+    }
 
-    fn call(
-        &mut self,
-        target: Target,
-    ) -> futures::future::FutureResult<Self::Service, io::Error> {
+    fn call(&mut self, target: Target) -> Self::Future {
+        let policy = self.policy;
+        let entries = self.inner.iter_mut().map(|(base_path, service, mount_kind, methods)| {
+            let base_path = *base_path;
+            let mount_kind = *mount_kind;
+            let methods = *methods;
+            service.call(target.clone()).map(move |service| (base_path, service, mount_kind, methods))
+        }).collect::<Vec<_>>();
+
+        Box::new(future::join_all(entries).map(move |entries| CompositeService { entries, policy }))
+    }
+}
+
+/// A `CompositeMakeService` with a [`Layer`] applied to every service it
+/// produces. Created by [`CompositeMakeService::layer`].
+pub struct LayeredCompositeMakeService<Target, ServiceError, Req, ResBody, ReqError, L> {
+    inner: CompositeMakeService<Target, ServiceError, Req, ResBody, ReqError>,
+    layer: L,
+}
+
+impl<Target, ServiceError, Req, ResBody, ReqError, L> Service<Target>
+    for LayeredCompositeMakeService<Target, ServiceError, Req, ResBody, ReqError, L>
+where
+    Target: Clone,
+    ResBody: NotFound<ResBody> + MethodNotAllowed<ResBody> + 'static,
+    Req: 'static,
+    ReqError: 'static,
+    L: Layer<Req, ResBody, ReqError> + Clone + 'static,
+{
+    type Error = ServiceError;
+    type Response = CompositeService<Req, ResBody, ReqError>;
+    type Future = Box<dyn Future<Item = Self::Response, Error = Self::Error>>;
+
+    fn poll_ready(&mut self) -> futures::Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
 
-        futures::future::join_all(self.inner.iter().map(|(path, service)| service.call(context).map(|i| (path, i)))).map(|services| Ok(CompositeService(services)))
+    fn call(&mut self, target: Target) -> Self::Future {
+        let layer = self.layer.clone();
+        Box::new(self.inner.call(target).map(move |CompositeService { entries, policy }| {
+            let wrapped = entries
+                .into_iter()
+                .map(|(base_path, service, mount_kind, methods)| {
+                    let wrapped: CompositedService<Req, ResBody, ReqError> = Box::new(layer.layer(service));
+                    (base_path, wrapped, mount_kind, methods)
+                })
+                .collect();
+            CompositeService { entries: wrapped, policy }
+        }))
     }
 }
 
-impl<Target, ServiceError, ReqBody, ResBody, ReqError> fmt::Debug for CompositeMakeService<Target, ServiceError, ReqBody, ResBody, ReqError>
+impl<Target, ServiceError, Req, ResBody, ReqError> fmt::Debug for CompositeMakeService<Target, ServiceError, Req, ResBody, ReqError>
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         // Get vector of base paths
-        let str_vec: Vec<&'static str> = self.0.iter().map(|&(base_path, _)| base_path).collect();
+        let str_vec: Vec<&'static str> = self.inner.iter().map(|&(base_path, _, _, _)| base_path).collect();
         write!(
             f,
             "CompositeMakeService accepting base paths: {:?}",
@@ -92,66 +334,423 @@ impl<Target, ServiceError, ReqBody, ResBody, ReqError> fmt::Debug for CompositeM
     }
 }
 
-impl<Target, ServiceError, ReqBody, ResBody, ReqError> Deref for CompositeMakeService<Target, ServiceError, ReqBody, ResBody, ReqError>
+impl<Target, ServiceError, Req, ResBody, ReqError> Deref for CompositeMakeService<Target, ServiceError, Req, ResBody, ReqError>
 {
-    type Target = CompositeMakeServiceVec<Target>;
+    type Target = CompositeMakeServiceVec<Target, ServiceError, Req, ResBody, ReqError>;
 
     fn deref(&self) -> &Self::Target {
         &self.inner
     }
 }
 
-impl<Target, ServiceError, ReqBody, ResBody, ReqError> DerefMut for CompositeMakeService<Target, ServiceError, ReqBody, ResBody, ReqError>
+impl<Target, ServiceError, Req, ResBody, ReqError> DerefMut for CompositeMakeService<Target, ServiceError, Req, ResBody, ReqError>
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.inner
     }
 }
 
-/// Wraps a vector of pairs, each consisting of a base path as a `&'static str`
-/// and a `Service` instance.
-pub struct CompositeService<ReqBody, ResBody, Error>(Vec<(&'static str, BoxedService<ReqBody, ResBody, Error>)>)
+/// Wraps a vector of entries, each consisting of a base path as a `&'static
+/// str`, a `Service` instance, a [`MountKind`] and the HTTP methods accepted
+/// at that base path (empty meaning "accept all"), plus the
+/// [`ReadinessPolicy`] used by `poll_ready`.
+pub struct CompositeService<Req, ResBody, Error>
 where
-    V: NotFound<V> + 'static,
-    W: 'static;
+    ResBody: NotFound<ResBody> + 'static,
+{
+    entries: Vec<(&'static str, CompositedService<Req, ResBody, Error>, MountKind, &'static [Method])>,
+    policy: ReadinessPolicy,
+}
+
+/// Splits a path into its non-empty segments, e.g. `/foo/bar/` becomes
+/// `["foo", "bar"]` and `/` becomes `[]`.
+fn path_segments(path: &str) -> Vec<&str> {
+    path.split('/').filter(|segment| !segment.is_empty()).collect()
+}
+
+/// Returns `true` if `base` is a segment-wise prefix of `path`, i.e. every
+/// segment of `base` matches the corresponding segment of `path` in order.
+fn base_path_matches(base: &[&str], path: &[&str]) -> bool {
+    base.len() <= path.len() && base.iter().zip(path.iter()).all(|(b, p)| b == p)
+}
+
+/// Rewrites `req`'s URI so its path has `base_path` removed from the front,
+/// stashing the original path and query in a [`FullPath`] extension so
+/// downstream handlers can still recover it.
+fn strip_base_path<Req: HttpRequest>(req: Req, base_path: &str) -> Req {
+    req.map_request(|mut inner| {
+        let full_path = inner
+            .uri()
+            .path_and_query()
+            .map(|path_and_query| path_and_query.as_str().to_owned())
+            .unwrap_or_else(|| inner.uri().path().to_owned());
+
+        let base_segments = path_segments(base_path);
+        let remaining_segments = &path_segments(inner.uri().path())[base_segments.len()..];
+        let stripped_path = format!("/{}", remaining_segments.join("/"));
+        let query = inner.uri().query().map(|query| query.to_owned());
+
+        let new_path_and_query = match query {
+            Some(query) => format!("{}?{}", stripped_path, query),
+            None => stripped_path,
+        };
+
+        let mut parts = inner.uri().clone().into_parts();
+        parts.path_and_query = Some(
+            new_path_and_query
+                .parse()
+                .expect("stripped path and query is a valid URI component"),
+        );
+        *inner.uri_mut() = hyper::Uri::from_parts(parts).expect("rebuilt URI is valid");
 
-impl<ReqBody, ResBody, Error> Service<Request<ReqBody>> for CompositeService<ReqBody, ResBody, Error>
+        inner.extensions_mut().insert(FullPath(full_path));
+        inner
+    })
+}
+
+impl<Req, ResBody, Error> Service<Req> for CompositeService<Req, ResBody, Error>
+where
+    Req: HttpRequest,
+    ResBody: NotFound<ResBody> + MethodNotAllowed<ResBody> + 'static,
 {
     type Error = Error;
     type Response = Response<ResBody>;
     type Future = Box<dyn Future<Item = Response<ResBody>, Error = Error>>;
 
-    fn call(&mut self, req: Request<Self::ReqBody>) -> Self::Future {
-        for &mut (base_path, ref mut service) in &mut self.0 {
-            if req.uri().path().starts_with(base_path) {
+    fn poll_ready(&mut self) -> futures::Poll<(), Self::Error> {
+        match self.policy {
+            ReadinessPolicy::ReadyAny => Ok(futures::Async::Ready(())),
+            ReadinessPolicy::ReadyAll => {
+                for (_, service, _, _) in self.entries.iter_mut() {
+                    match service.poll_ready()? {
+                        futures::Async::Ready(()) => {}
+                        futures::Async::NotReady => return Ok(futures::Async::NotReady),
+                    }
+                }
+                Ok(futures::Async::Ready(()))
+            }
+        }
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let req_segments = path_segments(req.http_request().uri().path());
+
+        // Rank every service whose base path matches the request path by how
+        // many leading segments it matches, most-specific first, so e.g.
+        // `/foo/bar` is tried before `/foo` when both are mounted and the
+        // request is for `/foo/bar/baz`. If the most specific match doesn't
+        // accept the request's method, fall through to the next-longest
+        // match that does, rather than 405ing on behalf of a mount the
+        // request was never headed for; a request to `/foo/bar` only 405s if
+        // every mount matching `/foo/bar` rejects its method.
+        let mut candidates: Vec<(usize, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &(base_path, _, _, _))| {
+                let base_segments = path_segments(base_path);
+                if base_path_matches(&base_segments, &req_segments) {
+                    Some((index, base_segments.len()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        candidates.sort_by_key(|&(_, matched_segments)| std::cmp::Reverse(matched_segments));
+
+        let mut method_not_allowed: Option<&'static [Method]> = None;
+        for (index, _) in candidates {
+            let (_, _, _, methods) = &self.entries[index];
+            let methods: &'static [Method] = *methods;
+            if methods.is_empty() || methods.contains(req.http_request().method()) {
+                let (base_path, service, mount_kind, _) = &mut self.entries[index];
+                let req = match mount_kind {
+                    MountKind::Full => req,
+                    MountKind::Nested => strip_base_path(req, base_path),
+                };
                 return service.call(req);
             }
+            if method_not_allowed.is_none() {
+                method_not_allowed = Some(methods);
+            }
         }
 
-        Box::new(future::ok(V::not_found()))
+        match method_not_allowed {
+            Some(methods) => Box::new(future::ok(ResBody::method_not_allowed(methods))),
+            None => Box::new(future::ok(ResBody::not_found())),
+        }
     }
 }
 
-impl<ReqBody, ResBody, Error> fmt::Debug for CompositeService<ReqBody, ResBody, Error>
+impl<Req, ResBody, Error> fmt::Debug for CompositeService<Req, ResBody, Error>
+where
+    ResBody: NotFound<ResBody> + 'static,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         // Get vector of base paths
-        let str_vec: Vec<&'static str> = self.0.iter().map(|&(base_path, _)| base_path).collect();
+        let str_vec: Vec<&'static str> = self.entries.iter().map(|&(base_path, _, _, _)| base_path).collect();
         write!(f, "CompositeService accepting base paths: {:?}", str_vec,)
     }
 }
 
-impl<ReqBody, ResBody, Error> Deref for CompositeService<ReqBody, ResBody, Error>
+impl<Req, ResBody, Error> Deref for CompositeService<Req, ResBody, Error>
+where
+    ResBody: NotFound<ResBody> + 'static,
 {
-    type Target = Vec<(&'static str, BoxedService<ReqBody, ResBody, Error>)>;
+    type Target = Vec<(&'static str, CompositedService<Req, ResBody, Error>, MountKind, &'static [Method])>;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.entries
     }
 }
 
-impl<ReqBody, ResBody, Error> DerefMut for CompositeService<ReqBody, ResBody, Error>
+impl<Req, ResBody, Error> DerefMut for CompositeService<Req, ResBody, Error>
+where
+    ResBody: NotFound<ResBody> + 'static,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_segments_splits_on_slash_ignoring_empties() {
+        assert_eq!(path_segments("/"), Vec::<&str>::new());
+        assert_eq!(path_segments(""), Vec::<&str>::new());
+        assert_eq!(path_segments("/foo/bar"), vec!["foo", "bar"]);
+        assert_eq!(path_segments("/foo/bar/"), vec!["foo", "bar"]);
+        assert_eq!(path_segments("foo/bar"), vec!["foo", "bar"]);
+    }
+
+    #[test]
+    fn base_path_matches_root_mount_matches_everything() {
+        assert!(base_path_matches(&path_segments("/"), &path_segments("/")));
+        assert!(base_path_matches(&path_segments("/"), &path_segments("/foo/bar")));
+    }
+
+    #[test]
+    fn base_path_matches_respects_segment_boundaries() {
+        assert!(base_path_matches(&path_segments("/foo"), &path_segments("/foo")));
+        assert!(base_path_matches(&path_segments("/foo"), &path_segments("/foo/bar")));
+        assert!(base_path_matches(&path_segments("/foo/"), &path_segments("/foo/bar/")));
+        assert!(!base_path_matches(&path_segments("/foo"), &path_segments("/foobar")));
+        assert!(!base_path_matches(&path_segments("/foo/bar"), &path_segments("/foo")));
+    }
+
+    #[test]
+    fn base_path_matches_prefers_longest_via_matched_segment_count() {
+        let candidates = ["/", "/api", "/api/v2"];
+        let req_segments = path_segments("/api/v2/foo");
+        let best = candidates
+            .iter()
+            .filter(|base| base_path_matches(&path_segments(base), &req_segments))
+            .max_by_key(|base| path_segments(base).len())
+            .unwrap();
+        assert_eq!(*best, "/api/v2");
+    }
+
+    #[test]
+    fn strip_base_path_on_root_mount_keeps_leading_slash() {
+        let req = Request::builder()
+            .uri("/foo/bar")
+            .body(hyper::Body::empty())
+            .unwrap();
+
+        let stripped = strip_base_path(req, "/");
+
+        assert_eq!(stripped.uri().path(), "/foo/bar");
+        assert_eq!(
+            stripped.extensions().get::<FullPath>(),
+            Some(&FullPath("/foo/bar".to_owned()))
+        );
+    }
+
+    #[test]
+    fn strip_base_path_removes_matched_prefix() {
+        let req = Request::builder()
+            .uri("/base/path/1/widgets?id=1")
+            .body(hyper::Body::empty())
+            .unwrap();
+
+        let stripped = strip_base_path(req, "/base/path/1");
+
+        assert_eq!(stripped.uri().path(), "/widgets");
+        assert_eq!(stripped.uri().query(), Some("id=1"));
+    }
+
+    struct StubService {
+        status: StatusCode,
+        ready: bool,
+    }
+
+    impl<Req> Service<Req> for StubService {
+        type Response = Response<Body>;
+        type Error = ();
+        type Future = future::FutureResult<Response<Body>, ()>;
+
+        fn poll_ready(&mut self) -> futures::Poll<(), Self::Error> {
+            if self.ready {
+                Ok(futures::Async::Ready(()))
+            } else {
+                Ok(futures::Async::NotReady)
+            }
+        }
+
+        fn call(&mut self, _req: Req) -> Self::Future {
+            future::ok(Response::builder().status(self.status).body(Body::empty()).unwrap())
+        }
+    }
+
+    fn composite_service(
+        entries: Vec<(&'static str, CompositedService<Request<Body>, Body, ()>, MountKind, &'static [Method])>,
+        policy: ReadinessPolicy,
+    ) -> CompositeService<Request<Body>, Body, ()> {
+        CompositeService { entries, policy }
+    }
+
+    fn request(method: Method, path: &str) -> Request<Body> {
+        Request::builder().method(method).uri(path).body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn call_routes_to_longest_matching_base_path() {
+        let mut composite = composite_service(
+            vec![
+                ("/", Box::new(StubService { status: StatusCode::OK, ready: true }), MountKind::Full, &[] as &[Method]),
+                ("/api/v2", Box::new(StubService { status: StatusCode::ACCEPTED, ready: true }), MountKind::Full, &[]),
+                ("/api/v2/foo", Box::new(StubService { status: StatusCode::CREATED, ready: true }), MountKind::Full, &[]),
+            ],
+            ReadinessPolicy::ReadyAny,
+        );
+
+        let response = composite.call(request(Method::GET, "/api/v2/foo/bar")).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[test]
+    fn call_falls_back_to_next_longest_mount_when_best_match_405s() {
+        let mut composite = composite_service(
+            vec![
+                ("/foo", Box::new(StubService { status: StatusCode::OK, ready: true }), MountKind::Full, &[Method::GET] as &[Method]),
+                ("/foo/bar", Box::new(StubService { status: StatusCode::CREATED, ready: true }), MountKind::Full, &[Method::POST]),
+            ],
+            ReadinessPolicy::ReadyAny,
+        );
+
+        // `/foo/bar` only accepts POST, so a GET falls back to the broader `/foo` mount.
+        let response = composite.call(request(Method::GET, "/foo/bar")).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn call_returns_405_with_allow_header_when_no_mount_accepts_the_method() {
+        let mut composite = composite_service(
+            vec![(
+                "/foo/bar",
+                Box::new(StubService { status: StatusCode::CREATED, ready: true }),
+                MountKind::Full,
+                &[Method::POST] as &[Method],
+            )],
+            ReadinessPolicy::ReadyAny,
+        );
+
+        let response = composite.call(request(Method::GET, "/foo/bar")).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers().get(ALLOW).unwrap(), "POST");
+    }
+
+    #[test]
+    fn poll_ready_ready_all_blocks_on_any_not_ready_service() {
+        let mut composite = composite_service(
+            vec![
+                ("/foo", Box::new(StubService { status: StatusCode::OK, ready: true }), MountKind::Full, &[] as &[Method]),
+                ("/bar", Box::new(StubService { status: StatusCode::OK, ready: false }), MountKind::Full, &[]),
+            ],
+            ReadinessPolicy::ReadyAll,
+        );
+
+        assert_eq!(composite.poll_ready().unwrap(), futures::Async::NotReady);
+    }
+
+    #[test]
+    fn poll_ready_ready_any_ignores_inner_readiness() {
+        let mut composite = composite_service(
+            vec![(
+                "/foo",
+                Box::new(StubService { status: StatusCode::OK, ready: false }),
+                MountKind::Full,
+                &[] as &[Method],
+            )],
+            ReadinessPolicy::ReadyAny,
+        );
+
+        assert_eq!(composite.poll_ready().unwrap(), futures::Async::Ready(()));
+    }
+
+    struct MakeStubService;
+
+    impl Service<()> for MakeStubService {
+        type Response = CompositedService<Request<Body>, Body, ()>;
+        type Error = ();
+        type Future = future::FutureResult<Self::Response, ()>;
+
+        fn poll_ready(&mut self) -> futures::Poll<(), Self::Error> {
+            Ok(futures::Async::Ready(()))
+        }
+
+        fn call(&mut self, _target: ()) -> Self::Future {
+            let service: CompositedService<Request<Body>, Body, ()> =
+                Box::new(StubService { status: StatusCode::OK, ready: true });
+            future::ok(service)
+        }
+    }
+
+    struct AddHeaderService {
+        inner: CompositedService<Request<Body>, Body, ()>,
+    }
+
+    impl Service<Request<Body>> for AddHeaderService {
+        type Response = Response<Body>;
+        type Error = ();
+        type Future = Box<dyn Future<Item = Response<Body>, Error = ()>>;
+
+        fn poll_ready(&mut self) -> futures::Poll<(), Self::Error> {
+            self.inner.poll_ready()
+        }
+
+        fn call(&mut self, req: Request<Body>) -> Self::Future {
+            Box::new(self.inner.call(req).map(|mut response| {
+                response
+                    .headers_mut()
+                    .insert("x-layer", hyper::header::HeaderValue::from_static("applied"));
+                response
+            }))
+        }
+    }
+
+    #[derive(Clone)]
+    struct AddHeaderLayer;
+
+    impl Layer<Request<Body>, Body, ()> for AddHeaderLayer {
+        type Service = AddHeaderService;
+
+        fn layer(&self, inner: CompositedService<Request<Body>, Body, ()>) -> Self::Service {
+            AddHeaderService { inner }
+        }
+    }
+
+    #[test]
+    fn layer_wraps_every_mounted_service() {
+        let mut make_service = CompositeMakeService::new();
+        make_service.push("/foo", &[], MakeStubService);
+        let mut layered = make_service.layer(AddHeaderLayer);
+
+        let mut composite = layered.call(()).wait().unwrap();
+        let response = composite.call(request(Method::GET, "/foo")).wait().unwrap();
+
+        assert_eq!(response.headers().get("x-layer").unwrap(), "applied");
     }
 }